@@ -1,23 +1,252 @@
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_int};
 use std::ptr;
+use std::sync::{Arc, Mutex};
 
 use anyhow::{Context, Result};
+use serde::Serialize;
 use swc_core::common::{
-    errors::{Handler, EmitterWriter},
-    FileName, SourceMap, Globals, GLOBALS, Mark,
+    errors::{Diagnostic, DiagnosticBuilder, DiagnosticId, Emitter as DiagnosticEmitter, Handler},
+    BytePos, FileName, LineCol, SourceMap, Globals, GLOBALS, Mark,
     sync::Lrc,
 };
+use swc_core::common::comments::{Comments, SingleThreadedComments};
+use swc_core::ecma::ast::{CallExpr, Callee, ExportAll, Expr, ImportDecl, Lit, NamedExport, Program};
 use swc_core::ecma::codegen::{text_writer::JsWriter, Config, Emitter};
-use swc_core::ecma::parser::{lexer::Lexer, Parser, StringInput, Syntax};
+use swc_core::ecma::parser::{lexer::Lexer, EsConfig, Parser, StringInput, Syntax, TsConfig};
+use swc_core::ecma::transforms::base::{fixer::fixer, hygiene::hygiene};
+use swc_core::ecma::transforms::compat::{es2015, es2016, es2017, es2018, es2019, es2020, es2021, es2022};
+use swc_core::ecma::transforms::react::{react, Options as ReactOptions, Runtime as JsxRuntime};
 use swc_core::ecma::transforms::typescript::strip;
+use swc_core::ecma::visit::{Visit, VisitWith};
 // use swc_core::ecma::visit::FoldWith; // fold_with replaced by apply
 
+/// How the generated source map should be delivered to the caller.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SourceMapMode {
+    /// Append a `//# sourceMappingURL=data:...;base64,...` comment to the emitted JS
+    /// and leave `out_sourcemap` unset.
+    Inline,
+    /// Return the source map JSON separately through `out_sourcemap`.
+    External,
+}
+
+impl SourceMapMode {
+    fn parse(raw: Option<&str>) -> Self {
+        match raw {
+            Some("inline") => SourceMapMode::Inline,
+            _ => SourceMapMode::External,
+        }
+    }
+}
+
+/// Owned, Rust-side configuration for the JSX/TSX lowering pass.
+///
+/// `swc_transpile_ts` always uses [`JsxConfig::default`]; `swc_transpile_ts_ex` lets
+/// callers override it via [`TranspileOptions`].
+#[derive(Clone)]
+struct JsxConfig {
+    factory: String,
+    fragment_factory: String,
+    development: bool,
+    automatic_runtime: bool,
+    import_source: String,
+}
+
+impl Default for JsxConfig {
+    fn default() -> Self {
+        Self {
+            factory: "React.createElement".to_string(),
+            fragment_factory: "React.Fragment".to_string(),
+            development: false,
+            automatic_runtime: false,
+            import_source: "react".to_string(),
+        }
+    }
+}
+
+/// The ECMAScript version the emitted JS must run on. Syntax introduced after `target`
+/// is downleveled by the `swc_ecma_transforms_compat` passes chained in [`transpile`];
+/// `EsNext` (the default) disables downleveling entirely.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum EsTarget {
+    Es2015,
+    Es2016,
+    Es2017,
+    Es2018,
+    Es2019,
+    Es2020,
+    Es2021,
+    Es2022,
+    EsNext,
+}
+
+impl Default for EsTarget {
+    fn default() -> Self {
+        EsTarget::EsNext
+    }
+}
+
+impl EsTarget {
+    fn parse(raw: Option<&str>) -> Self {
+        match raw {
+            Some("es2015") => EsTarget::Es2015,
+            Some("es2016") => EsTarget::Es2016,
+            Some("es2017") => EsTarget::Es2017,
+            Some("es2018") => EsTarget::Es2018,
+            Some("es2019") => EsTarget::Es2019,
+            Some("es2020") => EsTarget::Es2020,
+            Some("es2021") => EsTarget::Es2021,
+            Some("es2022") => EsTarget::Es2022,
+            _ => EsTarget::EsNext,
+        }
+    }
+}
+
+/// What kind of source is being parsed, driving `Syntax` selection directly instead of
+/// guessing from the filename extension.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MediaType {
+    JavaScript,
+    Jsx,
+    TypeScript,
+    Tsx,
+    Dts,
+}
+
+impl MediaType {
+    fn parse(raw: Option<&str>) -> Option<Self> {
+        match raw {
+            Some("javascript") => Some(MediaType::JavaScript),
+            Some("jsx") => Some(MediaType::Jsx),
+            Some("typescript") => Some(MediaType::TypeScript),
+            Some("tsx") => Some(MediaType::Tsx),
+            Some("dts") => Some(MediaType::Dts),
+            _ => None,
+        }
+    }
+
+    /// Best-effort guess for callers that don't pass a `MediaType` explicitly.
+    fn from_filename(filename: &str) -> Self {
+        if filename.ends_with(".d.ts") {
+            MediaType::Dts
+        } else if filename.ends_with(".tsx") {
+            MediaType::Tsx
+        } else if filename.ends_with(".ts") {
+            MediaType::TypeScript
+        } else if filename.ends_with(".jsx") {
+            MediaType::Jsx
+        } else if filename.ends_with(".mjs") || filename.ends_with(".cjs") || filename.ends_with(".js") {
+            MediaType::JavaScript
+        } else {
+            MediaType::TypeScript
+        }
+    }
+
+    fn syntax(self) -> Syntax {
+        match self {
+            MediaType::JavaScript | MediaType::Jsx => Syntax::Es(EsConfig {
+                jsx: self == MediaType::Jsx,
+                dynamic_import: true,
+                ..Default::default()
+            }),
+            MediaType::TypeScript | MediaType::Tsx | MediaType::Dts => Syntax::Typescript(TsConfig {
+                tsx: self == MediaType::Tsx,
+                decorators: true,
+                dts: self == MediaType::Dts,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+/// FFI-stable configuration for [`swc_transpile_ts_ex`]. Any `*const c_char` field left
+/// null falls back to the corresponding default (see [`JsxConfig`], [`EsTarget`], and
+/// [`MediaType`]).
+#[repr(C)]
+pub struct TranspileOptions {
+    pub jsx_factory: *const c_char,
+    pub jsx_fragment_factory: *const c_char,
+    pub jsx_development: c_int,
+    pub jsx_automatic_runtime: c_int,
+    pub jsx_import_source: *const c_char,
+    /// e.g. `"es2015"`..`"es2022"` or `"esnext"`; null/unrecognized means `esnext`.
+    pub target: *const c_char,
+    /// `"javascript"`, `"jsx"`, `"typescript"`, `"tsx"`, or `"dts"`; null/unrecognized
+    /// falls back to guessing from the filename extension.
+    pub media_type: *const c_char,
+}
+
+/// Reads an optional `*const c_char` field, falling back to `default` when the pointer
+/// is null or the field wasn't UTF-8.
+unsafe fn c_str_or(ptr: *const c_char, default: &str) -> String {
+    if ptr.is_null() {
+        return default.to_string();
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map(|s| s.to_string())
+        .unwrap_or_else(|_| default.to_string())
+}
+
+unsafe fn jsx_config_from_options(options: &TranspileOptions) -> JsxConfig {
+    let defaults = JsxConfig::default();
+    JsxConfig {
+        factory: c_str_or(options.jsx_factory, &defaults.factory),
+        fragment_factory: c_str_or(options.jsx_fragment_factory, &defaults.fragment_factory),
+        development: options.jsx_development != 0,
+        automatic_runtime: options.jsx_automatic_runtime != 0,
+        import_source: c_str_or(options.jsx_import_source, &defaults.import_source),
+    }
+}
+
+unsafe fn target_from_options(options: &TranspileOptions) -> EsTarget {
+    if options.target.is_null() {
+        return EsTarget::default();
+    }
+    EsTarget::parse(CStr::from_ptr(options.target).to_str().ok())
+}
+
+/// Resolves a raw `media_type` string pointer (from [`TranspileOptions`] or
+/// `swc_analyze_deps`'s own parameter) to a [`MediaType`], falling back to guessing from
+/// `filename` when the pointer is null or unrecognized.
+unsafe fn media_type_from_options(media_type: *const c_char, filename: &str) -> MediaType {
+    if media_type.is_null() {
+        return MediaType::from_filename(filename);
+    }
+    MediaType::parse(CStr::from_ptr(media_type).to_str().ok())
+        .unwrap_or_else(|| MediaType::from_filename(filename))
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn swc_transpile_ts(
     source: *const c_char,
     filename: *const c_char,
-    _source_map_mode: *const c_char, // Unused for now
+    source_map_mode: *const c_char,
+    out_js: *mut *mut c_char,
+    out_sourcemap: *mut *mut c_char,
+    out_error: *mut *mut c_char,
+) -> c_int {
+    swc_transpile_ts_ex(
+        source,
+        filename,
+        source_map_mode,
+        ptr::null(),
+        out_js,
+        out_sourcemap,
+        out_error,
+    )
+}
+
+/// Like [`swc_transpile_ts`], but accepts a [`TranspileOptions`] pointer to customize the
+/// JSX/TSX lowering pass. `options` may be null, in which case [`JsxConfig::default`] is
+/// used and behavior matches `swc_transpile_ts` exactly.
+#[no_mangle]
+pub unsafe extern "C" fn swc_transpile_ts_ex(
+    source: *const c_char,
+    filename: *const c_char,
+    source_map_mode: *const c_char,
+    options: *const TranspileOptions,
     out_js: *mut *mut c_char,
     out_sourcemap: *mut *mut c_char,
     out_error: *mut *mut c_char,
@@ -51,11 +280,94 @@ pub unsafe extern "C" fn swc_transpile_ts(
         }
     };
 
-    match transpile(source_str, filename_str) {
-        Ok(js) => {
+    let mode_str = if source_map_mode.is_null() {
+        None
+    } else {
+        match CStr::from_ptr(source_map_mode).to_str() {
+            Ok(s) => Some(s),
+            Err(e) => {
+                set_error(format!("Invalid source_map_mode encoding: {}", e));
+                return 1;
+            }
+        }
+    };
+    let mode = SourceMapMode::parse(mode_str);
+    let (jsx, target, media_type) = match options.as_ref() {
+        Some(options) => (
+            jsx_config_from_options(options),
+            target_from_options(options),
+            media_type_from_options(options.media_type, filename_str),
+        ),
+        None => (
+            JsxConfig::default(),
+            EsTarget::default(),
+            MediaType::from_filename(filename_str),
+        ),
+    };
+
+    match transpile(source_str, filename_str, mode, jsx, target, media_type) {
+        Ok((js, sourcemap)) => {
             let c_js = CString::new(js).unwrap_or_default();
             *out_js = c_js.into_raw();
-            *out_sourcemap = ptr::null_mut(); // Not implemented yet
+            *out_sourcemap = match sourcemap {
+                Some(map) => CString::new(map).unwrap_or_default().into_raw(),
+                None => ptr::null_mut(),
+            };
+            *out_error = ptr::null_mut();
+            0
+        }
+        Err(e) => {
+            set_error(format!("{:#}", e));
+            1
+        }
+    }
+}
+
+/// Parses `source` and returns its static/dynamic import and export-from dependencies as
+/// a JSON array of `{ specifier, start, end, line, col, is_dynamic, is_type_only }`
+/// objects, reusing the same lexer/parser setup `transpile` uses. On parse failure,
+/// `out_error` receives the same structured diagnostics JSON `swc_transpile_ts` returns.
+#[no_mangle]
+pub unsafe extern "C" fn swc_analyze_deps(
+    source: *const c_char,
+    filename: *const c_char,
+    media_type: *const c_char,
+    out_json: *mut *mut c_char,
+    out_error: *mut *mut c_char,
+) -> c_int {
+    let set_error = |err_msg: String| {
+        let c_err = CString::new(err_msg).unwrap_or_default();
+        *out_error = c_err.into_raw();
+        *out_json = ptr::null_mut();
+    };
+
+    if source.is_null() || filename.is_null() {
+        set_error("Source or filename is null".to_string());
+        return 1;
+    }
+
+    let source_str = match CStr::from_ptr(source).to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_error(format!("Invalid source encoding: {}", e));
+            return 1;
+        }
+    };
+
+    let filename_str = match CStr::from_ptr(filename).to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_error(format!("Invalid filename encoding: {}", e));
+            return 1;
+        }
+    };
+
+    let media_type = media_type_from_options(media_type, filename_str);
+
+    match analyze_deps(source_str, filename_str, media_type) {
+        Ok(json) => {
+            let c_json = CString::new(json).unwrap_or_default();
+            *out_json = c_json.into_raw();
             *out_error = ptr::null_mut();
             0
         }
@@ -73,64 +385,549 @@ pub unsafe extern "C" fn swc_free(ptr: *mut c_char) {
     }
 }
 
-fn transpile(source: &str, filename: &str) -> Result<String> {
+/// A [`DiagnosticEmitter`] that buffers diagnostics instead of writing them to a stream, so
+/// `transpile` can hand callers structured errors through `out_error` instead of text
+/// printed to the process's stderr.
+struct DiagnosticCollector {
+    diagnostics: Arc<Mutex<Vec<Diagnostic>>>,
+}
+
+impl DiagnosticEmitter for DiagnosticCollector {
+    fn emit(&mut self, db: &DiagnosticBuilder<'_>) {
+        self.diagnostics.lock().unwrap().push((**db).clone());
+    }
+}
+
+/// One diagnostic, serialized for `out_error`.
+#[derive(Serialize)]
+struct DiagnosticInfo {
+    message: String,
+    filename: String,
+    line: usize,
+    col: usize,
+    code: Option<String>,
+}
+
+fn diagnostics_to_json(cm: &Lrc<SourceMap>, diagnostics: &[Diagnostic]) -> String {
+    let infos: Vec<DiagnosticInfo> = diagnostics
+        .iter()
+        .map(|d| {
+            let (filename, line, col) = match d.span.primary_span() {
+                Some(span) => {
+                    let loc = cm.lookup_char_pos(span.lo());
+                    (loc.file.name.to_string(), loc.line, loc.col.0 + 1)
+                }
+                None => ("<unknown>".to_string(), 0, 0),
+            };
+            DiagnosticInfo {
+                message: d.message(),
+                filename,
+                line,
+                col,
+                code: d.code.as_ref().map(|code| match code {
+                    DiagnosticId::Error(s) => s.clone(),
+                    DiagnosticId::Lint(s) => s.clone(),
+                }),
+            }
+        })
+        .collect();
+    serde_json::to_string(&infos).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Lexes and parses `source` per `media_type`, the setup shared by `transpile` and
+/// `analyze_deps`. `comments`, when given, receives the source's comments so later passes
+/// (e.g. `transpile`'s `fixer`) can stay comment-aware; `analyze_deps` has no use for them
+/// and passes `None`. Parse errors are emitted to `handler` and returned as a placeholder
+/// error; callers should re-derive the real message from their own diagnostics buffer via
+/// [`diagnostics_to_json`].
+fn parse_source(
+    source: &str,
+    filename: &str,
+    media_type: MediaType,
+    comments: Option<&dyn Comments>,
+    cm: &Lrc<SourceMap>,
+    handler: &Handler,
+) -> Result<Program> {
+    let fm = cm.new_source_file(FileName::Real(filename.into()).into(), source.into());
+
+    let lexer = Lexer::new(
+        media_type.syntax(),
+        Default::default(),
+        StringInput::from(&*fm),
+        comments,
+    );
+    let mut parser = Parser::new_from(lexer);
+
+    parser.parse_program().map_err(|e| {
+        e.into_diagnostic(handler).emit();
+        anyhow::anyhow!("Failed to parse source")
+    })
+}
+
+/// One import/export-from dependency found by [`DepCollector`].
+#[derive(Serialize)]
+struct DependencyDescriptor {
+    specifier: String,
+    start: usize,
+    end: usize,
+    line: usize,
+    col: usize,
+    is_dynamic: bool,
+    is_type_only: bool,
+}
+
+/// Walks a parsed `Program` collecting static `import`/`export ... from`, dynamic
+/// `import()`, and type-only import dependency descriptors.
+struct DepCollector<'a> {
+    cm: &'a Lrc<SourceMap>,
+    deps: Vec<DependencyDescriptor>,
+}
+
+impl<'a> DepCollector<'a> {
+    fn push(&mut self, span: swc_core::common::Span, specifier: &str, is_dynamic: bool, is_type_only: bool) {
+        let loc = self.cm.lookup_char_pos(span.lo());
+        self.deps.push(DependencyDescriptor {
+            specifier: specifier.to_string(),
+            start: span.lo().0 as usize,
+            end: span.hi().0 as usize,
+            line: loc.line,
+            col: loc.col.0 + 1,
+            is_dynamic,
+            is_type_only,
+        });
+    }
+}
+
+impl<'a> Visit for DepCollector<'a> {
+    fn visit_import_decl(&mut self, node: &ImportDecl) {
+        self.push(node.src.span, &node.src.value, false, node.type_only);
+        node.visit_children_with(self);
+    }
+
+    fn visit_named_export(&mut self, node: &NamedExport) {
+        if let Some(src) = &node.src {
+            self.push(src.span, &src.value, false, node.type_only);
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_export_all(&mut self, node: &ExportAll) {
+        self.push(node.src.span, &node.src.value, false, node.type_only);
+        node.visit_children_with(self);
+    }
+
+    fn visit_call_expr(&mut self, node: &CallExpr) {
+        if let Callee::Import(_) = &node.callee {
+            if let Some(arg) = node.args.first() {
+                if let Expr::Lit(Lit::Str(specifier)) = &*arg.expr {
+                    self.push(specifier.span, &specifier.value, true, false);
+                }
+            }
+        }
+        node.visit_children_with(self);
+    }
+}
+
+fn analyze_deps(source: &str, filename: &str, media_type: MediaType) -> Result<String> {
     let globals = Globals::new();
     GLOBALS.set(&globals, || {
         let cm: Lrc<SourceMap> = Default::default();
-        
+
+        let diagnostics: Arc<Mutex<Vec<Diagnostic>>> = Default::default();
         let handler = Handler::with_emitter(
             true,
             false,
-            Box::new(EmitterWriter::new(
-                Box::new(std::io::stderr()),
-                Some(cm.clone()),
-                false,
-                true,
-            )),
+            Box::new(DiagnosticCollector {
+                diagnostics: diagnostics.clone(),
+            }),
         );
 
-        let fm = cm.new_source_file(FileName::Real(filename.into()).into(), source.into());
+        let program = parse_source(source, filename, media_type, None, &cm, &handler)
+            .map_err(|_| anyhow::anyhow!(diagnostics_to_json(&cm, &diagnostics.lock().unwrap())))?;
 
-        let mut syntax = Syntax::Typescript(Default::default());
-        if let Syntax::Typescript(config) = &mut syntax {
-            config.tsx = filename.ends_with(".tsx");
-            config.decorators = true;
-        }
+        let mut collector = DepCollector {
+            cm: &cm,
+            deps: vec![],
+        };
+        program.visit_with(&mut collector);
+
+        serde_json::to_string(&collector.deps).context("Failed to serialize dependencies")
+    })
+}
+
+fn transpile(
+    source: &str,
+    filename: &str,
+    source_map_mode: SourceMapMode,
+    jsx: JsxConfig,
+    target: EsTarget,
+    media_type: MediaType,
+) -> Result<(String, Option<String>)> {
+    let globals = Globals::new();
+    GLOBALS.set(&globals, || {
+        let cm: Lrc<SourceMap> = Default::default();
 
-        let lexer = Lexer::new(
-            syntax,
-            Default::default(),
-            StringInput::from(&*fm),
-            None,
+        let diagnostics: Arc<Mutex<Vec<Diagnostic>>> = Default::default();
+        let handler = Handler::with_emitter(
+            true,
+            false,
+            Box::new(DiagnosticCollector {
+                diagnostics: diagnostics.clone(),
+            }),
         );
 
-        let mut parser = Parser::new_from(lexer);
+        let comments = SingleThreadedComments::default();
 
-        let mut program = parser
-            .parse_program()
-            .map_err(|e| {
-                e.into_diagnostic(&handler).emit();
-                anyhow::anyhow!("Failed to parse TypeScript")
-            })?;
+        let mut program = parse_source(source, filename, media_type, Some(&comments), &cm, &handler)
+            .map_err(|_| anyhow::anyhow!(diagnostics_to_json(&cm, &diagnostics.lock().unwrap())))?;
+
+        if media_type == MediaType::Dts {
+            // Declaration files carry no runtime code; don't run transforms or emit.
+            return Ok((String::new(), None));
+        }
 
         // Apply transforms
         // Use apply directly if supported by Program, or map over program
-        program.apply(&mut strip(Mark::new(), Mark::new()));
+        let top_level_mark = Mark::new();
+        let unresolved_mark = Mark::new();
+        program.apply(&mut strip(top_level_mark, unresolved_mark));
+
+        let react_options = ReactOptions {
+            pragma: Some(jsx.factory.clone()),
+            pragma_frag: Some(jsx.fragment_factory.clone()),
+            development: Some(jsx.development),
+            runtime: Some(if jsx.automatic_runtime {
+                JsxRuntime::Automatic
+            } else {
+                JsxRuntime::Classic
+            }),
+            import_source: Some(jsx.import_source.clone()),
+            ..Default::default()
+        };
+        program.apply(&mut react(
+            cm.clone(),
+            Some(&comments),
+            react_options,
+            top_level_mark,
+            unresolved_mark,
+        ));
+
+        // Downlevel syntax the target runtime doesn't support, newest-first so each pass
+        // only ever has to deal with syntax from its own or earlier editions.
+        if target < EsTarget::Es2022 {
+            program.apply(&mut es2022::es2022(es2022::Config::default()));
+        }
+        if target < EsTarget::Es2021 {
+            program.apply(&mut es2021::es2021());
+        }
+        if target < EsTarget::Es2020 {
+            program.apply(&mut es2020::es2020(es2020::Config::default()));
+        }
+        if target < EsTarget::Es2019 {
+            program.apply(&mut es2019::es2019());
+        }
+        if target < EsTarget::Es2018 {
+            program.apply(&mut es2018::es2018(es2018::Config::default()));
+        }
+        if target < EsTarget::Es2017 {
+            program.apply(&mut es2017::es2017(unresolved_mark));
+        }
+        if target < EsTarget::Es2016 {
+            program.apply(&mut es2016::es2016());
+        }
+        if target < EsTarget::Es2015 {
+            program.apply(&mut es2015::es2015(
+                unresolved_mark,
+                Some(&comments),
+                es2015::Config::default(),
+            ));
+        }
+
+        // Rename identifiers that the transforms above left colliding, then re-insert the
+        // parens the emitted code needs to parse back to the same AST.
+        program.apply(&mut hygiene());
+        program.apply(&mut fixer(Some(&comments)));
 
         // Emit
         let mut buf = vec![];
+        let mut mappings: Vec<(BytePos, LineCol)> = vec![];
         {
             let mut emitter = Emitter {
                 cfg: Config::default(),
                 cm: cm.clone(),
-                comments: None,
-                wr: JsWriter::new(cm.clone(), "\n", &mut buf, None),
+                comments: Some(&comments),
+                wr: JsWriter::new(cm.clone(), "\n", &mut buf, Some(&mut mappings)),
             };
 
             emitter.emit_program(&program).context("Failed to emit JS")?;
         }
 
-        let js = String::from_utf8(buf).context("Output is not valid UTF-8")?;
-        Ok(js)
+        let mut js = String::from_utf8(buf).context("Output is not valid UTF-8")?;
+
+        let mut source_map = cm.build_source_map(&mappings);
+        source_map.set_source_contents(0, Some(source));
+
+        match source_map_mode {
+            SourceMapMode::Inline => {
+                let data_url = source_map
+                    .to_data_url()
+                    .context("Failed to encode source map as a data URL")?;
+                js.push_str("\n//# sourceMappingURL=");
+                js.push_str(&data_url);
+                js.push('\n');
+                Ok((js, None))
+            }
+            SourceMapMode::External => {
+                let mut sourcemap_buf = vec![];
+                source_map
+                    .to_writer(&mut sourcemap_buf)
+                    .context("Failed to serialize source map")?;
+                let sourcemap_json =
+                    String::from_utf8(sourcemap_buf).context("Source map is not valid UTF-8")?;
+                Ok((js, Some(sourcemap_json)))
+            }
+        }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transpile_at(source: &str, target: EsTarget) -> String {
+        let (js, _) = transpile(
+            source,
+            "input.ts",
+            SourceMapMode::External,
+            JsxConfig::default(),
+            target,
+            MediaType::TypeScript,
+        )
+        .expect("transpile should succeed");
+        js
+    }
+
+    #[test]
+    fn optional_chaining_and_class_fields_are_downleveled_for_es2015() {
+        let source = "class Foo { bar = 1; baz() { return this.bar?.toString(); } }";
+        let js = transpile_at(source, EsTarget::Es2015);
+        assert!(
+            !js.contains("?."),
+            "es2015 output still contains optional chaining: {js}"
+        );
+        assert!(
+            !js.contains("bar = 1"),
+            "es2015 output still has a class field shorthand: {js}"
+        );
+    }
+
+    #[test]
+    fn optional_chaining_and_class_fields_survive_esnext() {
+        let source = "class Foo { bar = 1; baz() { return this.bar?.toString(); } }";
+        let js = transpile_at(source, EsTarget::EsNext);
+        assert!(
+            js.contains("?."),
+            "esnext output should keep optional chaining: {js}"
+        );
+        assert!(
+            js.contains("bar = 1"),
+            "esnext output should keep the class field: {js}"
+        );
+    }
+
+    #[test]
+    fn decorator_output_is_preserved_after_hygiene_and_fixer() {
+        let source = r#"
+            function dec(target: any) { return target; }
+
+            @dec
+            class Foo {
+                @dec
+                bar() { return 1; }
+            }
+        "#;
+        let js = transpile_at(source, EsTarget::EsNext);
+        assert!(
+            js.contains("@dec"),
+            "decorators should survive the hygiene/fixer passes: {js}"
+        );
+        assert!(
+            js.contains("class Foo"),
+            "the decorated class should still be emitted: {js}"
+        );
+    }
+
+    #[test]
+    fn iife_keeps_its_wrapping_parens() {
+        let source = "(function () { return 1; })();";
+        let js = transpile_at(source, EsTarget::EsNext);
+        assert!(
+            js.trim_start().starts_with("(function"),
+            "fixer should keep the parens around an IIFE's function expression, \
+             otherwise it's reparsed as a function declaration instead of a call: {js}"
+        );
+    }
+
+    /// Minimal standard-alphabet base64 decoder, just enough to pull the JSON payload back
+    /// out of the `data:application/json;...;base64,...` URL `transpile` embeds for
+    /// `SourceMapMode::Inline` — not worth a crate dependency for a test assertion.
+    fn base64_decode(input: &str) -> Vec<u8> {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut table = [255u8; 256];
+        for (i, &b) in ALPHABET.iter().enumerate() {
+            table[b as usize] = i as u8;
+        }
+
+        let mut out = Vec::new();
+        let mut buf = 0u32;
+        let mut bits = 0u32;
+        for &b in input.as_bytes() {
+            if b == b'=' {
+                break;
+            }
+            let v = table[b as usize];
+            if v == 255 {
+                continue;
+            }
+            buf = (buf << 6) | v as u32;
+            bits += 6;
+            if bits >= 8 {
+                bits -= 8;
+                out.push((buf >> bits) as u8);
+            }
+        }
+        out
+    }
+
+    fn assert_v3_sourcemap(map: &serde_json::Value, filename: &str, source: &str) {
+        assert_eq!(map["version"], 3, "sourcemap should be version 3: {map}");
+        assert!(
+            map["sources"]
+                .as_array()
+                .expect("sources should be an array")
+                .iter()
+                .any(|s| s == filename),
+            "sources should contain {filename:?}: {map}"
+        );
+        assert_eq!(
+            map["sourcesContent"][0], source,
+            "sourcesContent[0] should be the original source"
+        );
+        assert!(
+            !map["mappings"]
+                .as_str()
+                .expect("mappings should be a string")
+                .is_empty(),
+            "mappings should not be empty: {map}"
+        );
+    }
+
+    #[test]
+    fn external_sourcemap_has_v3_shape_and_source_contents() {
+        let source = "const x: number = 1;";
+        let (_, sourcemap) = transpile(
+            source,
+            "input.ts",
+            SourceMapMode::External,
+            JsxConfig::default(),
+            EsTarget::default(),
+            MediaType::TypeScript,
+        )
+        .expect("transpile should succeed");
+        let sourcemap = sourcemap.expect("external mode should populate out_sourcemap");
+        let map: serde_json::Value =
+            serde_json::from_str(&sourcemap).expect("sourcemap should be valid JSON");
+        assert_v3_sourcemap(&map, "input.ts", source);
+    }
+
+    #[test]
+    fn inline_sourcemap_has_v3_shape_and_source_contents() {
+        let source = "const x: number = 1;";
+        let (js, sourcemap) = transpile(
+            source,
+            "input.ts",
+            SourceMapMode::Inline,
+            JsxConfig::default(),
+            EsTarget::default(),
+            MediaType::TypeScript,
+        )
+        .expect("transpile should succeed");
+        assert!(
+            sourcemap.is_none(),
+            "inline mode shouldn't also populate out_sourcemap"
+        );
+
+        let marker = "base64,";
+        let idx = js
+            .find(marker)
+            .expect("js should carry an inline sourceMappingURL data url");
+        let encoded = js[idx + marker.len()..].trim();
+        let decoded = base64_decode(encoded);
+        let map: serde_json::Value =
+            serde_json::from_slice(&decoded).expect("decoded data url should be valid JSON");
+        assert_v3_sourcemap(&map, "input.ts", source);
+    }
+
+    fn transpile_jsx(source: &str, jsx: JsxConfig) -> String {
+        let (js, _) = transpile(
+            source,
+            "input.tsx",
+            SourceMapMode::External,
+            jsx,
+            EsTarget::EsNext,
+            MediaType::Tsx,
+        )
+        .expect("transpile should succeed");
+        js
+    }
+
+    #[test]
+    fn classic_jsx_runtime_uses_configured_pragma() {
+        let source = "const el = <div>hi</div>;";
+        let js = transpile_jsx(source, JsxConfig::default());
+        assert!(
+            js.contains("React.createElement"),
+            "classic runtime should call the configured pragma: {js}"
+        );
+    }
+
+    #[test]
+    fn automatic_jsx_runtime_imports_from_jsx_runtime() {
+        let source = "const el = <div>hi</div>;";
+        let jsx = JsxConfig {
+            automatic_runtime: true,
+            ..JsxConfig::default()
+        };
+        let js = transpile_jsx(source, jsx);
+        assert!(
+            js.contains("jsx-runtime"),
+            "automatic runtime should import from the jsx-runtime module: {js}"
+        );
+    }
+
+    /// `analyze_deps` and `transpile` both go through `parse_source`; a decorator plus a
+    /// dynamic import should parse the same way for both, since they share one syntax
+    /// config instead of keeping their own copies in sync by hand.
+    #[test]
+    fn analyze_deps_and_transpile_share_the_same_parser_setup() {
+        let source = r#"
+            @dec
+            class Foo {}
+
+            import("./lazy.ts");
+        "#;
+
+        let deps_json = analyze_deps(source, "input.ts", MediaType::TypeScript)
+            .expect("analyze_deps should reuse the shared TS syntax config");
+        assert!(
+            deps_json.contains("./lazy.ts"),
+            "analyze_deps should find the dynamic import: {deps_json}"
+        );
+
+        let js = transpile_at(source, EsTarget::EsNext);
+        assert!(
+            js.contains("@dec") && js.contains("lazy.ts"),
+            "transpile should parse the same decorator/dynamic-import syntax analyze_deps did: {js}"
+        );
+    }
+}